@@ -0,0 +1,73 @@
+use std::fmt;
+
+use aws_sdk_s3::error::ProvideErrorMetadata;
+use aws_sdk_s3::error::SdkError;
+
+/// Errors returned by the operations on [`crate::r2::R2Manager`].
+///
+/// The variants distinguish the cases callers most often need to branch on —
+/// a missing key, a missing bucket, denied credentials — from transport level
+/// failures and anything else the service returns.
+#[derive(Debug)]
+pub enum R2Error {
+     /// The requested object key does not exist.
+     NoSuchKey,
+     /// The requested bucket does not exist.
+     NoSuchBucket,
+     /// The credentials are not authorized for the requested operation.
+     AccessDenied,
+     /// The request or the response body failed in transit (connection,
+     /// timeout, DNS, ...).
+     Transport(String),
+     /// Any other failure: a service error, carrying the error code and
+     /// message, or a local one such as an unreadable file, an invalid
+     /// presigning duration or a failed part of a multipart upload.
+     Other(String),
+}
+
+impl R2Error {
+     /// Classify an `aws_sdk_s3` [`SdkError`] into an [`R2Error`], mapping the
+     /// common S3 error codes to dedicated variants and keeping the code and
+     /// message for everything else.
+     pub(crate) fn from_sdk<E, R>(err: SdkError<E, R>) -> R2Error
+     where
+          E: ProvideErrorMetadata + std::error::Error + 'static,
+          R: fmt::Debug,
+     {
+          match err {
+               SdkError::TimeoutError(_) | SdkError::DispatchFailure(_) => {
+                    R2Error::Transport(err.to_string())
+               }
+               SdkError::ServiceError(ref service_err) => {
+                    let inner = service_err.err();
+                    match inner.code() {
+                         // HEAD responses carry no body, so a missing key
+                         // is reported as `NotFound` there.
+                         Some("NoSuchKey") | Some("NotFound") => R2Error::NoSuchKey,
+                         Some("NoSuchBucket") => R2Error::NoSuchBucket,
+                         Some("AccessDenied") => R2Error::AccessDenied,
+                         _ => R2Error::Other(format!(
+                              "{}: {}",
+                              inner.code().unwrap_or("Unknown"),
+                              inner.message().unwrap_or_default()
+                         )),
+                    }
+               }
+               other => R2Error::Other(other.to_string()),
+          }
+     }
+}
+
+impl fmt::Display for R2Error {
+     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+          match self {
+               R2Error::NoSuchKey => write!(f, "the requested object does not exist"),
+               R2Error::NoSuchBucket => write!(f, "the requested bucket does not exist"),
+               R2Error::AccessDenied => write!(f, "access denied for the requested operation"),
+               R2Error::Transport(message) => write!(f, "transport error: {}", message),
+               R2Error::Other(message) => write!(f, "{}", message),
+          }
+     }
+}
+
+impl std::error::Error for R2Error {}
@@ -26,4 +26,5 @@
 /// let bytes = r2_manager.get("test").await.unwrap();
 /// println!("{}", String::from_utf8(bytes).unwrap());
 /// ```
+pub mod error;
 pub mod r2;
\ No newline at end of file
@@ -1,19 +1,32 @@
 use std::sync::Arc;
-use once_cell::sync::Lazy;
+use std::time::Duration;
 
 use log::error;
 use log::info;
-use log::debug;
+
+use crate::error::R2Error;
 
 use aws_sdk_s3::primitives::ByteStream;
+use aws_sdk_s3::primitives::DateTime;
+use aws_sdk_s3::primitives::Length;
 use aws_sdk_s3::Client;
+use aws_config::BehaviorVersion;
 use aws_config::SdkConfig;
 use aws_sdk_s3::primitives::SdkBody;
+use aws_sdk_s3::config::Credentials;
 use aws_sdk_s3::config::Region;
+use aws_sdk_s3::config::SharedCredentialsProvider;
+use aws_sdk_s3::presigning::PresigningConfig;
+use aws_sdk_s3::types::{CompletedMultipartUpload, CompletedPart};
+
+use futures::stream::{self, StreamExt};
 
-static mut S3_CONFIG: Lazy<SdkConfig> = Lazy::new(|| { 
-     return SdkConfig::builder().build();
-});
+/// The S3 minimum part size (5 MiB). Every part of a multipart upload except
+/// the last one must be at least this large.
+const MIN_PART_SIZE: usize = 5 * 1024 * 1024;
+
+/// How many parts are uploaded concurrently to saturate the link.
+const UPLOAD_CONCURRENCY: usize = 8;
 
 /// A struct providing most necessary APIs to work with Cloudflare R2 object storage.
 #[derive(Debug, Clone)]
@@ -22,74 +35,127 @@ pub struct R2Manager {
      client: Arc<Client>
 }
 
+/// A single entry returned by [`R2Manager::list_objects`].
+#[derive(Debug, Clone)]
+pub struct R2Object {
+     /// The object key.
+     pub key: String,
+     /// The object size in bytes.
+     pub size: i64,
+     /// The object's entity tag, if the service returned one.
+     pub e_tag: Option<String>,
+}
+
+/// Object metadata returned by [`R2Manager::head_object`] without downloading
+/// the body.
+#[derive(Debug, Clone)]
+pub struct R2ObjectMetadata {
+     /// The object size in bytes.
+     pub size: i64,
+     /// The object's content type, if set.
+     pub content_type: Option<String>,
+     /// The object's entity tag, if the service returned one.
+     pub e_tag: Option<String>,
+     /// When the object was last modified, if the service returned it.
+     pub last_modified: Option<DateTime>,
+}
+
 impl R2Manager {
      /// Creates a new instance of R2Manager. The region is set to us-east-1 which aliases
      /// to auto. Read more here <https://developers.cloudflare.com/r2/api/s3/api/>.
+     ///
+     /// The credentials are built into a self-contained provider stored on the
+     /// instance, so a single process can hold several managers pointing at
+     /// different accounts or buckets at the same time.
      pub async fn new(
           bucket_name: &str,
-          cloudflare_kv_uri: &str, 
+          cloudflare_kv_uri: &str,
           cloudflare_kv_client_id: &str,
           cloudflare_kv_secret: &str
      ) -> R2Manager {
-          std::env::set_var("AWS_ACCESS_KEY_ID", cloudflare_kv_client_id);
-          std::env::set_var("AWS_SECRET_ACCESS_KEY", cloudflare_kv_secret);
-
-          let s3_config = aws_config::load_from_env()
-                .await
-                .into_builder()
-                .endpoint_url(cloudflare_kv_uri)
-                .region(Region::new("us-east-1"))
-                .build();
-
-          unsafe {
-               S3_CONFIG.clone_from(&s3_config);
-               let manager = R2Manager {
-                    bucket_name: bucket_name.into(),
-                    client: Arc::new(aws_sdk_s3::Client::new(&S3_CONFIG))
-               };
-               return manager;
+          R2Manager::with_config(
+               bucket_name,
+               cloudflare_kv_uri,
+               cloudflare_kv_client_id,
+               cloudflare_kv_secret,
+               "us-east-1")
+     }
+
+     /// Creates a new instance of R2Manager targeting the given region, for
+     /// S3-compatible providers (such as OVH) that require one.
+     pub async fn new_with_region(
+          bucket_name: &str,
+          cloudflare_kv_uri: &str,
+          cloudflare_kv_client_id: &str,
+          cloudflare_kv_secret: &str,
+          region: &str
+     ) -> R2Manager {
+          R2Manager::with_config(
+               bucket_name,
+               cloudflare_kv_uri,
+               cloudflare_kv_client_id,
+               cloudflare_kv_secret,
+               region)
+     }
+
+     /// Build a manager with a self-contained credentials provider and no
+     /// shared global state.
+     fn with_config(
+          bucket_name: &str,
+          cloudflare_kv_uri: &str,
+          cloudflare_kv_client_id: &str,
+          cloudflare_kv_secret: &str,
+          region: &str
+     ) -> R2Manager {
+          let credentials = Credentials::new(
+               cloudflare_kv_client_id,
+               cloudflare_kv_secret,
+               None,
+               None,
+               "cloudflare-r2-rs");
+
+          let s3_config = SdkConfig::builder()
+               .credentials_provider(SharedCredentialsProvider::new(credentials))
+               .endpoint_url(cloudflare_kv_uri)
+               .region(Region::new(region.to_string()))
+               .behavior_version(BehaviorVersion::latest())
+               .build();
+
+          R2Manager {
+               bucket_name: bucket_name.into(),
+               client: Arc::new(Client::new(&s3_config))
           }
      }
-     
+
      /// Get the bucket name of the R2Manager.
      pub fn get_bucket_name(&self) -> &str {
           &self.bucket_name
      }
 
      /// Create a bucket.
-     pub async fn create_bucket(&self) {
-          let create_bucket_request = self.client
+     pub async fn create_bucket(&self) -> Result<(), R2Error> {
+          self.client
                .create_bucket()
-               .bucket(&self.bucket_name);
-
-          let result = create_bucket_request.send().await;
+               .bucket(&self.bucket_name)
+               .send()
+               .await
+               .map_err(R2Error::from_sdk)?;
 
-          if result.is_ok() {
-               debug!("{:?}", result.unwrap());
-               info!("Created successfully {}", self.bucket_name);
-          }
-          else {
-               debug!("{:?}", result.unwrap_err());
-               error!("Creation of {} failed.", self.bucket_name);
-          }
+          info!("Created successfully {}", self.bucket_name);
+          Ok(())
      }
 
      /// Delete a bucket.
-     pub async fn delete_bucket(&self) {
-          let delete_bucket_request = self.client
+     pub async fn delete_bucket(&self) -> Result<(), R2Error> {
+          self.client
                .delete_bucket()
-               .bucket(&self.bucket_name);
-
-          let result = delete_bucket_request.send().await;
+               .bucket(&self.bucket_name)
+               .send()
+               .await
+               .map_err(R2Error::from_sdk)?;
 
-          if result.is_ok() {
-               debug!("{:?}", result.unwrap());
-               info!("Deleted successfully {}", self.bucket_name);
-          }
-          else {
-               debug!("{:?}", result.unwrap_err());
-               error!("Deletion of {} failed.", self.bucket_name);
-          }
+          info!("Deleted successfully {}", self.bucket_name);
+          Ok(())
      }
 
      /// Upload an object in &[u8] format.
@@ -102,7 +168,7 @@ impl R2Manager {
           object_name: &str, 
           object_bytes: &[u8],
           cache_control: Option<&str>, 
-          content_type: Option<&str>) {
+          content_type: Option<&str>) -> Result<(), R2Error> {
           let stream = ByteStream::new(SdkBody::from(object_bytes));
           let mut upload_request = self.client
                .put_object()
@@ -113,66 +179,339 @@ impl R2Manager {
           if let Some(cache_control) = cache_control {
                upload_request = upload_request.cache_control(cache_control);
           }
-          
+
           if let Some(content_type) = content_type {
                upload_request = upload_request.content_type(content_type);
           }
 
-          let result = upload_request.send().await;
+          upload_request.send().await.map_err(R2Error::from_sdk)?;
+
+          info!("Uploaded successfully {} to {}", object_name, self.bucket_name);
+          Ok(())
+     }
+
+     /// Upload a large object using the S3 multipart workflow.
+     ///
+     /// The file at `path` is split into parts of `part_size` bytes (the S3
+     /// minimum of 5 MiB is enforced for every part except the last) and the
+     /// parts are uploaded concurrently. If any part fails the whole upload is
+     /// aborted so dangling parts don't accrue storage charges.
+     /// ```
+     /// r2manager.upload_multipart("big.bin", "/tmp/big.bin", 8 * 1024 * 1024, Some("application/octet-stream")).await;
+     /// ```
+     pub async fn upload_multipart(
+          &self,
+          object_name: &str,
+          path: impl AsRef<std::path::Path>,
+          part_size: usize,
+          content_type: Option<&str>) -> Result<(), R2Error> {
+          let path = path.as_ref().to_path_buf();
+          let part_size = part_size.max(MIN_PART_SIZE);
 
-          if result.is_ok() {
-               debug!("{:?}", result.unwrap());
-               info!("Uploaded successfully {} to {}", object_name, self.bucket_name);
+          let file_len = tokio::fs::metadata(&path)
+               .await
+               .map_err(|e| R2Error::Other(format!("unable to read {}: {}", path.display(), e)))?
+               .len();
+
+          let mut create_request = self.client
+               .create_multipart_upload()
+               .bucket(&self.bucket_name)
+               .key(object_name);
+
+          if let Some(content_type) = content_type {
+               create_request = create_request.content_type(content_type);
           }
-          else {
-               debug!("{:?}", result.unwrap_err());
-               error!("Upload of {} to {} failed.", object_name, self.bucket_name);
+
+          let created = create_request.send().await.map_err(R2Error::from_sdk)?;
+          let upload_id = created.upload_id().unwrap_or_default().to_string();
+          let part_count = file_len.div_ceil(part_size as u64).max(1);
+
+          let parts = stream::iter(0..part_count)
+               .map(|index| {
+                    let client = self.client.clone();
+                    let bucket = self.bucket_name.clone();
+                    let key = object_name.to_string();
+                    let upload_id = upload_id.clone();
+                    let path = path.clone();
+                    async move {
+                         let offset = index * part_size as u64;
+                         let length = (part_size as u64).min(file_len - offset);
+                         let part_number = (index + 1) as i32;
+                         let body = ByteStream::read_from()
+                              .path(&path)
+                              .offset(offset)
+                              .length(Length::Exact(length))
+                              .build()
+                              .await?;
+                         let uploaded = client
+                              .upload_part()
+                              .bucket(&bucket)
+                              .key(&key)
+                              .upload_id(&upload_id)
+                              .part_number(part_number)
+                              .body(body)
+                              .send()
+                              .await?;
+                         Ok::<CompletedPart, Box<dyn std::error::Error + Send + Sync>>(
+                              CompletedPart::builder()
+                                   .part_number(part_number)
+                                   .set_e_tag(uploaded.e_tag().map(String::from))
+                                   .build())
+                    }
+               })
+               .buffer_unordered(UPLOAD_CONCURRENCY)
+               .collect::<Vec<_>>()
+               .await;
+
+          let mut completed_parts = Vec::with_capacity(parts.len());
+          for part in parts {
+               match part {
+                    Ok(part) => completed_parts.push(part),
+                    Err(e) => {
+                         error!("Part upload of {} to {} failed, aborting.", object_name, self.bucket_name);
+                         let _ = self.client
+                              .abort_multipart_upload()
+                              .bucket(&self.bucket_name)
+                              .key(object_name)
+                              .upload_id(&upload_id)
+                              .send()
+                              .await;
+                         return Err(R2Error::Other(e.to_string()));
+                    }
+               }
           }
+
+          completed_parts.sort_by_key(|part| part.part_number());
+
+          let completed = CompletedMultipartUpload::builder()
+               .set_parts(Some(completed_parts))
+               .build();
+
+          self.client
+               .complete_multipart_upload()
+               .bucket(&self.bucket_name)
+               .key(object_name)
+               .upload_id(&upload_id)
+               .multipart_upload(completed)
+               .send()
+               .await
+               .map_err(R2Error::from_sdk)?;
+
+          info!("Uploaded successfully {} to {}", object_name, self.bucket_name);
+          Ok(())
      }
 
      /// Get an object in Vec<u8> form.
      pub async fn get(
-          &self, 
-          object_name: &str) -> Option<Vec<u8>> {
-          let get_request = self.client
+          &self,
+          object_name: &str) -> Result<Vec<u8>, R2Error> {
+          let result = self.client
                .get_object()
                .bucket(&self.bucket_name)
                .key(object_name)
                .send()
-               .await;
+               .await
+               .map_err(R2Error::from_sdk)?;
 
-          if get_request.is_ok() {
-               let result = get_request.unwrap();
-               debug!("{:?}", result);
-               info!("Got successfully {} from {}", object_name, self.bucket_name);
-               let bytes = result.body.collect().await.unwrap().into_bytes().to_vec();
-               return Some(bytes);
-          }
-          else {
-               debug!("{:?}", get_request.unwrap_err());
-               error!("Unable to get {} from {}.", object_name, self.bucket_name);
-               None
+          let bytes = result.body
+               .collect()
+               .await
+               .map_err(|e| R2Error::Transport(e.to_string()))?
+               .into_bytes()
+               .to_vec();
+
+          info!("Got successfully {} from {}", object_name, self.bucket_name);
+          Ok(bytes)
+     }
+
+     /// Generate a time-limited presigned URL for downloading an object, so a
+     /// client can `GET` it directly from R2 without routing the bytes through
+     /// the application server. The URL is valid for `expires_in`.
+     pub async fn presigned_get_url(
+          &self,
+          object_name: &str,
+          expires_in: Duration) -> Result<String, R2Error> {
+          let config = PresigningConfig::expires_in(expires_in)
+               .map_err(|e| R2Error::Other(e.to_string()))?;
+
+          let presigned = self.client
+               .get_object()
+               .bucket(&self.bucket_name)
+               .key(object_name)
+               .presigned(config)
+               .await
+               .map_err(R2Error::from_sdk)?;
+
+          Ok(presigned.uri().to_string())
+     }
+
+     /// Generate a time-limited presigned URL for uploading an object, so a
+     /// client can `PUT` directly to R2 without routing the bytes through the
+     /// application server. The URL is valid for `expires_in`.
+     pub async fn presigned_put_url(
+          &self,
+          object_name: &str,
+          expires_in: Duration) -> Result<String, R2Error> {
+          let config = PresigningConfig::expires_in(expires_in)
+               .map_err(|e| R2Error::Other(e.to_string()))?;
+
+          let presigned = self.client
+               .put_object()
+               .bucket(&self.bucket_name)
+               .key(object_name)
+               .presigned(config)
+               .await
+               .map_err(R2Error::from_sdk)?;
+
+          Ok(presigned.uri().to_string())
+     }
+
+     /// List the objects in the bucket, transparently following the
+     /// `continuation_token` until the result is no longer truncated so the
+     /// caller gets every key without managing tokens by hand.
+     ///
+     /// `prefix` narrows the listing to keys starting with it. With a
+     /// `delimiter` (such as `Some("/")`) only the keys of a single
+     /// "directory" level are returned: keys rolled up into common prefixes
+     /// are left out, and the common prefixes themselves are not returned.
+     pub async fn list_objects(
+          &self,
+          prefix: Option<&str>,
+          delimiter: Option<&str>) -> Result<Vec<R2Object>, R2Error> {
+          let mut objects = Vec::new();
+          let mut continuation_token: Option<String> = None;
+
+          loop {
+               let mut list_request = self.client
+                    .list_objects_v2()
+                    .bucket(&self.bucket_name);
+
+               if let Some(prefix) = prefix {
+                    list_request = list_request.prefix(prefix);
+               }
+
+               if let Some(delimiter) = delimiter {
+                    list_request = list_request.delimiter(delimiter);
+               }
+
+               if let Some(token) = &continuation_token {
+                    list_request = list_request.continuation_token(token);
+               }
+
+               let result = list_request.send().await.map_err(R2Error::from_sdk)?;
+
+               for object in result.contents() {
+                    objects.push(R2Object {
+                         key: object.key().unwrap_or_default().to_string(),
+                         size: object.size().unwrap_or_default(),
+                         e_tag: object.e_tag().map(String::from),
+                    });
+               }
+
+               if result.is_truncated().unwrap_or(false) {
+                    continuation_token = result.next_continuation_token().map(String::from);
+               }
+               else {
+                    break;
+               }
           }
+
+          info!("Listed {} objects from {}", objects.len(), self.bucket_name);
+          Ok(objects)
+     }
+
+     /// Get an object as a [`ByteStream`] without buffering it into memory, so
+     /// the caller can stream large objects straight to a file or socket.
+     pub async fn get_stream(
+          &self,
+          object_name: &str) -> Result<ByteStream, R2Error> {
+          let result = self.client
+               .get_object()
+               .bucket(&self.bucket_name)
+               .key(object_name)
+               .send()
+               .await
+               .map_err(R2Error::from_sdk)?;
+
+          info!("Got successfully {} from {}", object_name, self.bucket_name);
+          Ok(result.body)
+     }
+
+     /// Get a byte range of an object as a [`ByteStream`] by setting the
+     /// `Range` header, for partial or resumable downloads. `start` and `end`
+     /// are inclusive byte offsets.
+     pub async fn get_range(
+          &self,
+          object_name: &str,
+          start: u64,
+          end: u64) -> Result<ByteStream, R2Error> {
+          let result = self.client
+               .get_object()
+               .bucket(&self.bucket_name)
+               .key(object_name)
+               .range(format!("bytes={}-{}", start, end))
+               .send()
+               .await
+               .map_err(R2Error::from_sdk)?;
+
+          info!("Got range {}-{} of {} from {}", start, end, object_name, self.bucket_name);
+          Ok(result.body)
+     }
+
+     /// Copy an object within the bucket server-side, without round-tripping
+     /// the bytes through the client. Handy for renaming or duplicating keys.
+     pub async fn copy_object(
+          &self,
+          src_key: &str,
+          dest_key: &str) -> Result<(), R2Error> {
+          self.client
+               .copy_object()
+               .bucket(&self.bucket_name)
+               .key(dest_key)
+               .copy_source(format!("{}/{}", self.bucket_name, src_key))
+               .send()
+               .await
+               .map_err(R2Error::from_sdk)?;
+
+          info!("Copied successfully {} to {} in {}", src_key, dest_key, self.bucket_name);
+          Ok(())
+     }
+
+     /// Fetch an object's metadata (size, content type, ETag, last-modified)
+     /// without downloading the body.
+     pub async fn head_object(
+          &self,
+          object_name: &str) -> Result<R2ObjectMetadata, R2Error> {
+          let result = self.client
+               .head_object()
+               .bucket(&self.bucket_name)
+               .key(object_name)
+               .send()
+               .await
+               .map_err(R2Error::from_sdk)?;
+
+          info!("Got metadata of {} from {}", object_name, self.bucket_name);
+          Ok(R2ObjectMetadata {
+               size: result.content_length().unwrap_or_default(),
+               content_type: result.content_type().map(String::from),
+               e_tag: result.e_tag().map(String::from),
+               last_modified: result.last_modified().copied(),
+          })
      }
 
      /// Delete an object.
      pub async fn delete(
-          &self, 
-          object_name: &str) {
-          let delete_request = self.client
+          &self,
+          object_name: &str) -> Result<(), R2Error> {
+          self.client
                .delete_object()
                .bucket(&self.bucket_name)
-               .key(object_name);
-
-          let result = delete_request.send().await;
+               .key(object_name)
+               .send()
+               .await
+               .map_err(R2Error::from_sdk)?;
 
-          if result.is_ok() {
-               debug!("{:?}", result.unwrap());
-               info!("Deleted successfully {} from {}", object_name, self.bucket_name);
-          }
-          else {
-               debug!("{:?}", result.unwrap_err());
-               error!("Deletion of {} from {} failed.", object_name, self.bucket_name);
-          }
+          info!("Deleted successfully {} from {}", object_name, self.bucket_name);
+          Ok(())
      }
 }
\ No newline at end of file